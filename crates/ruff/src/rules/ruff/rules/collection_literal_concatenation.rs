@@ -6,6 +6,7 @@ use ruff_diagnostics::{AutofixKind, Diagnostic, Edit, Fix, Violation};
 use ruff_macros::{derive_message_formats, violation};
 use ruff_python_ast::helpers::has_comments;
 use ruff_python_ast::verbatim_ast;
+use ruff_python_semantic::spanless::SpanlessEq;
 
 use crate::checkers::ast::Checker;
 use crate::registry::AsRule;
@@ -47,97 +48,263 @@ fn make_splat_elts(
     new_elts
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Type {
     List,
     Tuple,
+    Set,
+    Dict,
 }
 
-/// Recursively merge all the tuples and lists in the expression.
-fn concatenate_expressions(expr: &Expr) -> Option<(verbatim_ast::Expr, Type)> {
-    let Expr::BinOp(ast::ExprBinOp { left, op: Operator::Add, right, range: _ }) = expr else {
+/// Whether evaluating `expr` a second time is guaranteed to be equivalent to reusing its first
+/// value. Calls (and anything containing one) fail this: `f()` may have side effects or return a
+/// different value each time it's invoked, so `[f()] + [f()]` is not safe to rewrite as
+/// `[f()] * 2`, even though the two operands are textually identical.
+fn is_duplicable(expr: &Expr) -> bool {
+    match expr {
+        Expr::Constant(_) | Expr::Name(_) => true,
+        Expr::Attribute(ast::ExprAttribute { value, .. }) => is_duplicable(value),
+        Expr::List(ast::ExprList { elts, .. })
+        | Expr::Tuple(ast::ExprTuple { elts, .. })
+        | Expr::Set(ast::ExprSet { elts, .. }) => elts.iter().all(is_duplicable),
+        _ => false,
+    }
+}
+
+/// Returns `Some` if `expr` is a `list`/`tuple` built from `+`-concatenation of two structurally
+/// identical operands (e.g. `[x] + [x]`), which is really a repetition in disguise. Operands are
+/// compared with [`SpanlessEq`] rather than source text, so reformatting (e.g. `[ x ]` vs `[x]`)
+/// doesn't hide a real duplicate. Operands that aren't safe to evaluate twice (see
+/// [`is_duplicable`]) are left to the ordinary splice path in [`concatenate_expressions`], which
+/// preserves each occurrence as its own element instead of collapsing them into one.
+fn as_repetition(checker: &Checker, expr: &Expr) -> Option<String> {
+    let Expr::BinOp(ast::ExprBinOp {
+        left,
+        op: Operator::Add,
+        right,
+        range: _,
+    }) = expr
+    else {
         return None;
     };
 
-    let new_left = match left.as_ref() {
-        Expr::BinOp(ast::ExprBinOp { .. }) => match concatenate_expressions(left) {
-            Some((new_left, _)) => new_left,
-            None => verbatim_ast::Expr::from(left),
-        },
+    if !matches!(left.as_ref(), Expr::List(_) | Expr::Tuple(_))
+        || !matches!(right.as_ref(), Expr::List(_) | Expr::Tuple(_))
+    {
+        return None;
+    }
+
+    if !is_duplicable(left) || !is_duplicable(right) {
+        return None;
+    }
+
+    if !SpanlessEq::eq(left, right) {
+        return None;
+    }
+
+    let left_text = checker.locator.slice(left.range());
+    Some(format!("{left_text} * 2"))
+}
+
+/// Recursively merge all the tuples, lists, sets, and dicts in the expression.
+fn concatenate_expressions(expr: &Expr) -> Option<(verbatim_ast::Expr, Type)> {
+    match expr {
+        Expr::BinOp(ast::ExprBinOp {
+            left,
+            op: Operator::Add,
+            right,
+            range: _,
+        }) => concatenate_operands(left, right, Operator::Add),
+        Expr::BinOp(ast::ExprBinOp {
+            left,
+            op: Operator::BitOr,
+            right,
+            range: _,
+        }) => concatenate_operands(left, right, Operator::BitOr),
+        _ => None,
+    }
+}
+
+fn concatenate_operands(
+    left: &Expr,
+    right: &Expr,
+    op: Operator,
+) -> Option<(verbatim_ast::Expr, Type)> {
+    let new_left = match left {
+        Expr::BinOp(ast::ExprBinOp { op: inner_op, .. }) if *inner_op == op => {
+            match concatenate_expressions(left) {
+                Some((new_left, _)) => new_left,
+                None => verbatim_ast::Expr::from(left),
+            }
+        }
         _ => verbatim_ast::Expr::from(left),
     };
 
-    let new_right = match right.as_ref() {
-        Expr::BinOp(ast::ExprBinOp { .. }) => match concatenate_expressions(right) {
-            Some((new_right, _)) => new_right,
-            None => verbatim_ast::Expr::from(right),
-        },
+    let new_right = match right {
+        Expr::BinOp(ast::ExprBinOp { op: inner_op, .. }) if *inner_op == op => {
+            match concatenate_expressions(right) {
+                Some((new_right, _)) => new_right,
+                None => verbatim_ast::Expr::from(right),
+            }
+        }
         _ => verbatim_ast::Expr::from(right),
     };
 
+    // Dict merges (`{...} | {...}`) have a different element shape (key-value
+    // pairs rather than bare expressions), so they're handled on their own path. Use the
+    // already-flattened `new_left`/`new_right` (not `left`/`right`) so that chained merges like
+    // `{'a': 1} | {'b': 2} | {'c': 3}` keep matching as dicts all the way down.
+    if op == Operator::BitOr && (is_dict(&new_left) || is_dict(&new_right)) {
+        return concatenate_dicts(&new_left, &new_right);
+    }
+
     // Figure out which way the splat is, and the type of the collection.
     let (type_, splat_element, other_elements, splat_at_left) = match (&new_left, &new_right) {
-        (Expr::List(ast::ExprList { elts: l_elts, .. }), _) => (
+        (Expr::List(ast::ExprList { elts: l_elts, .. }), _) if op == Operator::Add => (
             Type::List,
             new_right,
             l_elts.iter().map(verbatim_ast::Expr::from).collect(),
             false,
         ),
-        (Expr::Tuple(ast::ExprTuple { elts: l_elts, .. }), _) => (
+        (Expr::Tuple(ast::ExprTuple { elts: l_elts, .. }), _) if op == Operator::Add => (
             Type::Tuple,
             new_right,
             l_elts.iter().map(verbatim_ast::Expr::from).collect(),
             false,
         ),
-        (_, Expr::List(ast::ExprList { elts: r_elts, .. })) => (
+        (_, Expr::List(ast::ExprList { elts: r_elts, .. })) if op == Operator::Add => (
             Type::List,
             new_left,
             r_elts.iter().map(verbatim_ast::Expr::from).collect(),
             true,
         ),
-        (_, Expr::Tuple(ast::ExprTuple { elts: r_elts, .. })) => (
+        (_, Expr::Tuple(ast::ExprTuple { elts: r_elts, .. })) if op == Operator::Add => (
             Type::Tuple,
             new_left,
             r_elts.iter().map(verbatim_ast::Expr::from).collect(),
             true,
         ),
+        (Expr::Set(ast::ExprSet { elts: l_elts, .. }), _) if op == Operator::BitOr => (
+            Type::Set,
+            new_right,
+            l_elts.iter().map(verbatim_ast::Expr::from).collect(),
+            false,
+        ),
+        (_, Expr::Set(ast::ExprSet { elts: r_elts, .. })) if op == Operator::BitOr => (
+            Type::Set,
+            new_left,
+            r_elts.iter().map(verbatim_ast::Expr::from).collect(),
+            true,
+        ),
         _ => return None,
     };
 
     let new_elts = match &splat_element {
         // We'll be a bit conservative here; only calls, names and attribute accesses
-        // will be considered as splat elements.
+        // will be considered as splat elements. Such an operand could have overridden
+        // `__add__`/`__radd__` or `__or__`/`__ror__`, which is why the caller only offers
+        // the fix as a suggestion.
         Expr::Call(_) | Expr::Attribute(_) | Expr::Name(_) => {
             make_splat_elts(splat_element, other_elements, splat_at_left)
         }
-        // If the splat element is itself a list/tuple, insert them in the other list/tuple.
+        // If the splat element is itself a list/tuple/set, insert them in the other
+        // list/tuple/set.
         Expr::List(ast::ExprList { elts, .. }) if matches!(type_, Type::List) => {
             other_elements.iter().chain(elts.iter()).cloned().collect()
         }
         Expr::Tuple(ast::ExprTuple { elts, .. }) if matches!(type_, Type::Tuple) => {
             other_elements.iter().chain(elts.iter()).cloned().collect()
         }
+        Expr::Set(ast::ExprSet { elts, .. }) if matches!(type_, Type::Set) => {
+            other_elements.iter().chain(elts.iter()).cloned().collect()
+        }
         _ => return None,
     };
 
     let new_expr = match type_ {
         Type::List => verbatim_ast::Expr::List(verbatim_ast::ExprList { elts: new_elts }),
         Type::Tuple => verbatim_ast::Expr::Tuple(verbatim_ast::ExprTuple { elts: new_elts }),
+        Type::Set => verbatim_ast::Expr::Set(verbatim_ast::ExprSet { elts: new_elts }),
+        Type::Dict => unreachable!("dict merges are handled by `concatenate_dicts`"),
     };
 
     Some((new_expr, type_))
 }
 
+fn is_dict(expr: &Expr) -> bool {
+    matches!(expr, Expr::Dict(_))
+}
+
+/// Merge a `{...} | {...}` dict union into a single dict literal, unpacking a call, name, or
+/// attribute operand with `**`. `**` entries inside a dict operand are copied over as they are,
+/// and a dict literal that isn't an operand of `|` (such as `{**a, **{'b': 1}}`) isn't simplified.
+fn concatenate_dicts(left: &Expr, right: &Expr) -> Option<(verbatim_ast::Expr, Type)> {
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+
+    for operand in [left, right] {
+        match operand {
+            Expr::Dict(ast::ExprDict {
+                keys: d_keys,
+                values: d_values,
+                range: _,
+            }) => {
+                for (key, value) in d_keys.iter().zip(d_values.iter()) {
+                    keys.push(key.as_ref().map(verbatim_ast::Expr::from));
+                    values.push(verbatim_ast::Expr::from(value));
+                }
+            }
+            // Only calls, names, and attribute accesses are considered safe to unpack with
+            // `**`; anything else, we bail and leave the expression alone.
+            Expr::Call(_) | Expr::Attribute(_) | Expr::Name(_) => {
+                keys.push(None);
+                values.push(verbatim_ast::Expr::Verbatim(verbatim_ast::ExprVerbatim {
+                    range: operand.range(),
+                }));
+            }
+            _ => return None,
+        }
+    }
+
+    Some((
+        verbatim_ast::Expr::Dict(verbatim_ast::ExprDict { keys, values }),
+        Type::Dict,
+    ))
+}
+
 /// RUF005
 pub(crate) fn collection_literal_concatenation(checker: &mut Checker, expr: &Expr) {
-    // If the expression is already a child of an addition, we'll have analyzed it already.
-    if matches!(
-        checker.semantic_model().expr_parent(),
-        Some(Expr::BinOp(ast::ExprBinOp {
-            op: Operator::Add,
-            ..
-        }))
-    ) {
+    // If our parent is a concatenation that uses the *same* operator we do, it'll flatten us into
+    // itself (see `concatenate_operands`'s `if *inner_op == op`), so we'll have analyzed this
+    // expression already as part of that larger one. A parent with a different operator (e.g. a
+    // `BitOr` sitting above an `Add`, as in `[1] + [2] | foo`) treats us as an opaque operand
+    // instead, so we still need to report ourselves.
+    if let Some(Expr::BinOp(ast::ExprBinOp { op: parent_op, .. })) =
+        checker.semantic_model().expr_parent()
+    {
+        let same_op = matches!(
+            expr,
+            Expr::BinOp(ast::ExprBinOp { op, .. }) if op == parent_op
+        );
+        if matches!(parent_op, Operator::Add | Operator::BitOr) && same_op {
+            return;
+        }
+    }
+
+    if let Some(contents) = as_repetition(checker, expr) {
+        let mut diagnostic = Diagnostic::new(
+            CollectionLiteralConcatenation {
+                expr: contents.clone(),
+            },
+            expr.range(),
+        );
+        if checker.patch(diagnostic.kind.rule()) && !has_comments(expr, checker.locator) {
+            diagnostic.set_fix(Fix::suggested(Edit::range_replacement(
+                contents,
+                expr.range(),
+            )));
+        }
+        checker.diagnostics.push(diagnostic);
         return;
     }
 
@@ -148,7 +315,7 @@ pub(crate) fn collection_literal_concatenation(checker: &mut Checker, expr: &Exp
     let contents = match type_ {
         // Wrap the new expression in parentheses if it was a tuple.
         Type::Tuple => format!("({})", checker.verbatim_generator().expr(&new_expr)),
-        Type::List => checker.verbatim_generator().expr(&new_expr),
+        Type::List | Type::Set | Type::Dict => checker.verbatim_generator().expr(&new_expr),
     };
     let mut diagnostic = Diagnostic::new(
         CollectionLiteralConcatenation {
@@ -159,7 +326,8 @@ pub(crate) fn collection_literal_concatenation(checker: &mut Checker, expr: &Exp
     if checker.patch(diagnostic.kind.rule()) {
         if !has_comments(expr, checker.locator) {
             // This suggestion could be unsafe if the non-literal expression in the
-            // expression has overridden the `__add__` (or `__radd__`) magic methods.
+            // expression has overridden the `__add__`/`__radd__` (for `+`) or
+            // `__or__`/`__ror__` (for `|`) magic methods.
             diagnostic.set_fix(Fix::suggested(Edit::range_replacement(
                 contents,
                 expr.range(),
@@ -168,3 +336,41 @@ pub(crate) fn collection_literal_concatenation(checker: &mut Checker, expr: &Exp
     }
     checker.diagnostics.push(diagnostic);
 }
+
+#[cfg(test)]
+mod tests {
+    use rustpython_parser::ast::Expr;
+
+    use super::{concatenate_expressions, is_duplicable, Type};
+
+    fn parse(source: &str) -> Expr {
+        rustpython_parser::parse_expression(source, "<test>").unwrap()
+    }
+
+    #[test]
+    fn duplicable_literals_and_names() {
+        assert!(is_duplicable(&parse("[1, 2, x]")));
+        assert!(is_duplicable(&parse("(x, y.z)")));
+    }
+
+    #[test]
+    fn calls_are_not_duplicable() {
+        assert!(!is_duplicable(&parse("[f()]")));
+        assert!(!is_duplicable(&parse("[x, [f()]]")));
+    }
+
+    #[test]
+    fn set_union_is_recognized() {
+        let (_, type_) = concatenate_expressions(&parse("{1, 2} | other")).unwrap();
+        assert_eq!(type_, Type::Set);
+    }
+
+    #[test]
+    fn chained_dict_merges_all_flatten() {
+        // Regression test: `concatenate_dicts` used to be called with the original, un-recursed
+        // operands, so a merge of 3+ dicts never matched past the first pair.
+        let (_, type_) =
+            concatenate_expressions(&parse("{'a': 1} | {'b': 2} | {'c': 3}")).unwrap();
+        assert_eq!(type_, Type::Dict);
+    }
+}
@@ -2,7 +2,7 @@ use std::iter::FusedIterator;
 use std::ops::Index;
 
 use ruff_index::{newtype_index, IndexSlice, IndexVec};
-use ruff_python_ast::{Expr, Stmt};
+use ruff_python_ast::{AstNode, Expr, NodeKind, Stmt};
 use ruff_text_size::{Ranged, TextRange};
 
 use crate::BranchId;
@@ -25,12 +25,21 @@ struct NodeWithParent<'a> {
     parent: Option<NodeId>,
     /// The branch ID of this node, if any.
     branch: Option<BranchId>,
+    /// The ID of this node's first child, if any.
+    first_child: Option<NodeId>,
+    /// The ID of this node's next sibling (the next child of the same parent), if any.
+    next_sibling: Option<NodeId>,
 }
 
 /// The nodes of a program indexed by [`NodeId`]
 #[derive(Debug, Default)]
 pub struct Nodes<'a> {
     nodes: IndexVec<NodeId, NodeWithParent<'a>>,
+    /// The most recently inserted child of each node, so that [`insert`] can link a newly
+    /// inserted node onto the end of its parent's sibling chain in O(1).
+    ///
+    /// [`insert`]: Nodes::insert
+    last_child: IndexVec<NodeId, Option<NodeId>>,
 }
 
 impl<'a, 'ast> Nodes<'a> {
@@ -41,11 +50,24 @@ impl<'a, 'ast> Nodes<'a> {
         parent: Option<NodeId>,
         branch: Option<BranchId>,
     ) -> NodeId {
-        self.nodes.push(NodeWithParent {
+        let node_id = self.nodes.push(NodeWithParent {
             node,
             parent,
             branch,
-        })
+            first_child: None,
+            next_sibling: None,
+        });
+        self.last_child.push(None);
+
+        if let Some(parent) = parent {
+            match self.last_child[parent] {
+                Some(last_child) => self.nodes[last_child].next_sibling = Some(node_id),
+                None => self.nodes[parent].first_child = Some(node_id),
+            }
+            self.last_child[parent] = Some(node_id);
+        }
+
+        node_id
     }
 
     /// Return the [`NodeId`] of the parent node.
@@ -67,6 +89,92 @@ impl<'a, 'ast> Nodes<'a> {
             nodes: &self.nodes,
         }
     }
+
+    /// Returns the [`NodeId`] of the given node's next sibling (the next node inserted under the
+    /// same parent), if any.
+    #[inline]
+    pub fn next_sibling(&self, node_id: NodeId) -> Option<NodeId> {
+        self.nodes[node_id].next_sibling
+    }
+
+    /// Returns the [`NodeId`] of the given node's previous sibling, if any.
+    ///
+    /// Unlike [`next_sibling`], this isn't a direct pointer lookup: it walks the parent's
+    /// children from the front, since each node only stores a forward link. This is O(children)
+    /// rather than O(1), which is fine for the common case (looking at what's right before a
+    /// statement in its block) but isn't a substitute for [`next_sibling`] in a loop.
+    ///
+    /// [`next_sibling`]: Nodes::next_sibling
+    pub fn prev_sibling(&self, node_id: NodeId) -> Option<NodeId> {
+        let parent = self.nodes[node_id].parent?;
+        let mut prev = None;
+        let mut current = self.nodes[parent].first_child;
+        while let Some(candidate) = current {
+            if candidate == node_id {
+                return prev;
+            }
+            prev = Some(candidate);
+            current = self.nodes[candidate].next_sibling;
+        }
+        None
+    }
+
+    /// Returns an iterator over the direct children of `node_id`, in insertion (i.e. source)
+    /// order.
+    #[inline]
+    pub fn children(&self, node_id: NodeId) -> ChildrenIter<'_, 'a> {
+        ChildrenIter {
+            next: self.nodes[node_id].first_child,
+            nodes: &self.nodes,
+        }
+    }
+
+    /// Returns an iterator over all descendants of `node_id` (children, grandchildren, etc.), in
+    /// pre-order.
+    #[inline]
+    pub fn descendants(&self, node_id: NodeId) -> DescendantsIter<'_, 'a> {
+        // The stack is popped from the end, so the first child needs to be pushed last.
+        let mut stack: Vec<NodeId> = self.children(node_id).collect();
+        stack.reverse();
+        DescendantsIter {
+            nodes: &self.nodes,
+            stack,
+        }
+    }
+
+    /// Returns the nearest *strict* ancestor of `node_id` for which `predicate` returns `true`,
+    /// if any.
+    ///
+    /// [`enclosing`] is built on this; reach for it first if it fits, since it reads better at
+    /// the call site than a bare predicate.
+    ///
+    /// [`enclosing`]: Nodes::enclosing
+    pub fn first_ancestor_matching(
+        &self,
+        node_id: NodeId,
+        mut predicate: impl FnMut(NodeRef<'a>) -> bool,
+    ) -> Option<NodeId> {
+        self.ancestor_ids(node_id)
+            .skip(1)
+            .find(|&id| predicate(self[id]))
+    }
+
+    /// Returns an iterator over the statement ancestors of `node_id`, nearest first, skipping
+    /// over any expression ancestors in between (e.g. `x if y else z` sitting inside a `return`).
+    pub fn ancestor_statements(&self, node_id: NodeId) -> impl Iterator<Item = &'a Stmt> + '_ {
+        self.ancestor_ids(node_id)
+            .skip(1)
+            .filter_map(|id| self[id].as_statement())
+    }
+
+    /// Returns the nearest ancestor of `node_id` whose [`NodeKind`] is `kind`, if any.
+    ///
+    /// Typical uses are finding the nearest enclosing function, loop, `try`, class, or
+    /// comprehension to decide whether some construct (`break`, `yield`, `await`, ...) is valid
+    /// at this point in the tree.
+    pub fn enclosing(&self, node_id: NodeId, kind: NodeKind) -> Option<NodeId> {
+        self.first_ancestor_matching(node_id, |node| node.kind() == kind)
+    }
 }
 
 impl<'a, 'ast> Index<NodeId> for Nodes<'a> {
@@ -104,6 +212,55 @@ impl Iterator for AncestorIter<'_, '_> {
 
 impl FusedIterator for AncestorIter<'_, '_> {}
 
+/// An iterator over the direct children of a node, in source order. See [`Nodes::children`].
+pub struct ChildrenIter<'iter, 'a> {
+    nodes: &'iter IndexSlice<NodeId, NodeWithParent<'a>>,
+    next: Option<NodeId>,
+}
+
+impl Iterator for ChildrenIter<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next.take()?;
+        self.next = self.nodes[next].next_sibling;
+        Some(next)
+    }
+}
+
+impl FusedIterator for ChildrenIter<'_, '_> {}
+
+/// A pre-order iterator over all descendants of a node. See [`Nodes::descendants`].
+pub struct DescendantsIter<'iter, 'a> {
+    nodes: &'iter IndexSlice<NodeId, NodeWithParent<'a>>,
+    stack: Vec<NodeId>,
+}
+
+impl Iterator for DescendantsIter<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_id = self.stack.pop()?;
+
+        // Push children in reverse so the leftmost child is popped (and thus visited) first.
+        let mut children: Vec<NodeId> = {
+            let mut next = self.nodes[node_id].first_child;
+            let mut children = Vec::new();
+            while let Some(child) = next {
+                children.push(child);
+                next = self.nodes[child].next_sibling;
+            }
+            children
+        };
+        children.reverse();
+        self.stack.extend(children);
+
+        Some(node_id)
+    }
+}
+
+impl FusedIterator for DescendantsIter<'_, '_> {}
+
 /// A reference to an AST node. Like [`ruff_python_ast::AnyNodeRef`], but wraps the node
 /// itself (like [`Stmt`]) rather than the narrowed type (like [`ruff_python_ast::StmtAssign`]).
 ///
@@ -142,6 +299,14 @@ impl<'a> NodeRef<'a> {
     pub fn is_expression(&self) -> bool {
         self.as_expression().is_some()
     }
+
+    /// Returns the [`NodeKind`] of this node.
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            NodeRef::Stmt(stmt) => stmt.kind(),
+            NodeRef::Expr(expr) => expr.kind(),
+        }
+    }
 }
 
 impl Ranged for NodeRef<'_> {
@@ -164,3 +329,80 @@ impl<'a> From<&'a Stmt<'a>> for NodeRef<'a> {
         NodeRef::Stmt(stmt)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ruff_python_ast as ast;
+    use ruff_python_parser::parse_suite;
+
+    use super::{NodeId, NodeKind, NodeRef, Nodes};
+
+    fn parse(source: &str) -> Vec<ast::Stmt> {
+        parse_suite(source, "<test>").unwrap()
+    }
+
+    /// Builds the same small tree (an `if` statement with a test expression and two body
+    /// statements) for each test below: `if True:\n    pass\n    break\n`.
+    fn build_if_tree(body: &[ast::Stmt]) -> (Nodes<'_>, NodeId, NodeId, NodeId, NodeId) {
+        let ast::Stmt::If(if_stmt) = &body[0] else {
+            panic!("expected an `if` statement");
+        };
+
+        let mut nodes = Nodes::default();
+        let if_id = nodes.insert(NodeRef::Stmt(&body[0]), None, None);
+        let test_id = nodes.insert(NodeRef::Expr(&if_stmt.test), Some(if_id), None);
+        let pass_id = nodes.insert(NodeRef::Stmt(&if_stmt.body[0]), Some(if_id), None);
+        let break_id = nodes.insert(NodeRef::Stmt(&if_stmt.body[1]), Some(if_id), None);
+
+        (nodes, if_id, test_id, pass_id, break_id)
+    }
+
+    #[test]
+    fn children_are_in_source_order_with_matching_siblings() {
+        let body = parse("if True:\n    pass\n    break\n");
+        let (nodes, if_id, test_id, pass_id, break_id) = build_if_tree(&body);
+
+        assert_eq!(
+            nodes.children(if_id).collect::<Vec<_>>(),
+            vec![test_id, pass_id, break_id]
+        );
+
+        assert_eq!(nodes.next_sibling(test_id), Some(pass_id));
+        assert_eq!(nodes.next_sibling(pass_id), Some(break_id));
+        assert_eq!(nodes.next_sibling(break_id), None);
+
+        assert_eq!(nodes.prev_sibling(break_id), Some(pass_id));
+        assert_eq!(nodes.prev_sibling(pass_id), Some(test_id));
+        assert_eq!(nodes.prev_sibling(test_id), None);
+    }
+
+    #[test]
+    fn descendants_are_pre_order() {
+        // Regression test for a bug where the traversal stack was seeded in source order instead
+        // of reverse, which visited children in reverse source order rather than pre-order
+        // (fixed in a follow-up commit).
+        let body = parse("if True:\n    pass\n    break\n");
+        let (nodes, if_id, test_id, pass_id, break_id) = build_if_tree(&body);
+
+        assert_eq!(
+            nodes.descendants(if_id).collect::<Vec<_>>(),
+            vec![test_id, pass_id, break_id]
+        );
+    }
+
+    #[test]
+    fn ancestor_helpers_find_the_enclosing_if() {
+        let body = parse("if True:\n    pass\n    break\n");
+        let (nodes, if_id, _test_id, pass_id, _break_id) = build_if_tree(&body);
+
+        assert_eq!(
+            nodes.first_ancestor_matching(pass_id, |node| node.is_statement()),
+            Some(if_id)
+        );
+        assert_eq!(nodes.enclosing(pass_id, NodeKind::StmtIf), Some(if_id));
+        assert_eq!(
+            nodes.ancestor_statements(pass_id).collect::<Vec<_>>(),
+            vec![&body[0]]
+        );
+    }
+}
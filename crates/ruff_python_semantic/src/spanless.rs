@@ -0,0 +1,524 @@
+use std::hash::{Hash, Hasher};
+
+use ruff_python_ast::{self as ast, AnyNodeRef, AstNode, NodeKind};
+use rustc_hash::FxHasher;
+
+/// How deep [`SpanlessHash`]/[`SpanlessEq`] will recurse before giving up and treating the
+/// remaining subtree as opaque.
+///
+/// Without a cap, a pathological input (e.g. a deeply nested binary expression) could blow the
+/// stack before the lint that's using this facility ever gets a chance to bail out.
+const MAX_DEPTH: u32 = 128;
+
+/// Computes a hash of an AST node that depends only on its *structure* — operators, literal
+/// values, identifier names, and the shape of its children — and not on where it sits in the
+/// source file.
+///
+/// Two nodes that are spelled identically but appear at different [`TextRange`]s hash the same,
+/// which is the point: it lets a lint bucket candidate subtrees (e.g. `if`/`elif` branches, dict
+/// keys, `match` arms) by structural hash and then confirm true duplicates with [`SpanlessEq`].
+///
+/// [`TextRange`]: ruff_text_size::TextRange
+pub struct SpanlessHash<'a> {
+    hasher: FxHasher,
+    depth: u32,
+    _marker: std::marker::PhantomData<AnyNodeRef<'a>>,
+}
+
+impl<'a> SpanlessHash<'a> {
+    pub fn of<N>(node: &'a N) -> u64
+    where
+        N: AstNode,
+    {
+        Self::of_ref(node.as_any_node_ref())
+    }
+
+    /// Like [`SpanlessHash::of`], but over an already-erased [`AnyNodeRef`].
+    fn of_ref(node: AnyNodeRef<'a>) -> u64 {
+        let mut hasher = Self {
+            hasher: FxHasher::default(),
+            depth: 0,
+            _marker: std::marker::PhantomData,
+        };
+        hasher.hash_node(node);
+        hasher.hasher.finish()
+    }
+
+    fn hash_node(&mut self, node: AnyNodeRef<'a>) {
+        self.hash_kind(node.kind());
+
+        if self.depth >= MAX_DEPTH {
+            // Treat everything past the depth limit as an opaque leaf: its kind still contributes
+            // to the hash, but nothing below it does, so subtrees that differ only past the limit
+            // hash the same.
+            return;
+        }
+
+        match node {
+            AnyNodeRef::ExprBinOp(ast::ExprBinOp { op, left, right, .. }) => {
+                self.hash_op(*op);
+                self.recurse(left.as_any_node_ref());
+                self.recurse(right.as_any_node_ref());
+            }
+            AnyNodeRef::ExprUnaryOp(ast::ExprUnaryOp { op, operand, .. }) => {
+                op.hash(&mut self.hasher);
+                self.recurse(operand.as_any_node_ref());
+            }
+            AnyNodeRef::ExprBoolOp(ast::ExprBoolOp { op, values, .. }) => {
+                op.hash(&mut self.hasher);
+                for value in values {
+                    self.recurse(value.as_any_node_ref());
+                }
+            }
+            AnyNodeRef::ExprCompare(ast::ExprCompare {
+                left,
+                ops,
+                comparators,
+                ..
+            }) => {
+                for op in ops {
+                    op.hash(&mut self.hasher);
+                }
+                self.recurse(left.as_any_node_ref());
+                for comparator in comparators {
+                    self.recurse(comparator.as_any_node_ref());
+                }
+            }
+            AnyNodeRef::ExprName(ast::ExprName { id, .. }) => {
+                id.hash(&mut self.hasher);
+            }
+            AnyNodeRef::ExprNumberLiteral(literal) => {
+                format!("{literal:?}").hash(&mut self.hasher);
+            }
+            AnyNodeRef::ExprStringLiteral(ast::ExprStringLiteral { value, .. }) => {
+                value.to_str().hash(&mut self.hasher);
+            }
+            AnyNodeRef::ExprBooleanLiteral(ast::ExprBooleanLiteral { value, .. }) => {
+                value.hash(&mut self.hasher);
+            }
+            AnyNodeRef::ExprCall(ast::ExprCall {
+                func, arguments, ..
+            }) => {
+                self.recurse(func.as_any_node_ref());
+                for arg in &arguments.args {
+                    self.recurse(arg.as_any_node_ref());
+                }
+                for keyword in &arguments.keywords {
+                    if let Some(arg) = &keyword.arg {
+                        arg.id.hash(&mut self.hasher);
+                    }
+                    self.recurse(keyword.value.as_any_node_ref());
+                }
+            }
+            AnyNodeRef::ExprAttribute(ast::ExprAttribute { value, attr, .. }) => {
+                attr.id.hash(&mut self.hasher);
+                self.recurse(value.as_any_node_ref());
+            }
+            AnyNodeRef::ExprStarred(ast::ExprStarred { value, .. }) => {
+                self.recurse(value.as_any_node_ref());
+            }
+            // `{1, 2}` and `{2, 1}` should hash the same: hash each element independently and
+            // fold in the sorted digests rather than the elements in source order.
+            AnyNodeRef::ExprSet(ast::ExprSet { elts, .. }) => {
+                self.hash_unordered(elts.iter().map(AstNode::as_any_node_ref));
+            }
+            AnyNodeRef::ExprDict(ast::ExprDict { items, .. }) => {
+                let mut digests: Vec<u64> = items
+                    .iter()
+                    .map(|item| {
+                        let key_digest = item
+                            .key
+                            .as_ref()
+                            .map(|key| self.child_digest(key.as_any_node_ref()))
+                            .unwrap_or_default();
+                        let value_digest = self.child_digest(item.value.as_any_node_ref());
+                        key_digest ^ value_digest.rotate_left(1)
+                    })
+                    .collect();
+                digests.sort_unstable();
+                digests.hash(&mut self.hasher);
+            }
+            _ => {
+                // Fall back to the generic child walk for everything else: still structural
+                // (range-free), just without a special-cased payload.
+                for child in node.children() {
+                    self.recurse(child);
+                }
+            }
+        }
+    }
+
+    fn recurse(&mut self, node: AnyNodeRef<'a>) {
+        self.depth += 1;
+        self.hash_node(node);
+        self.depth -= 1;
+    }
+
+    fn hash_unordered(&mut self, nodes: impl Iterator<Item = AnyNodeRef<'a>>) {
+        let mut digests: Vec<u64> = nodes.map(|node| self.child_digest(node)).collect();
+        digests.sort_unstable();
+        digests.hash(&mut self.hasher);
+    }
+
+    /// Hashes `node` as its own standalone subtree, one level deeper than `self`, and returns the
+    /// resulting digest without feeding it into `self`'s hasher.
+    ///
+    /// Used wherever children need to be hashed independently before being combined (e.g. sorted,
+    /// for an unordered container) — unlike calling the public [`SpanlessHash::of`], this keeps
+    /// `self`'s depth threaded through, so `MAX_DEPTH` still bounds recursion through nodes that
+    /// hash this way (dict items, set elements) instead of getting reset to zero at each boundary.
+    fn child_digest(&self, node: AnyNodeRef<'a>) -> u64 {
+        let mut sub = Self {
+            hasher: FxHasher::default(),
+            depth: self.depth + 1,
+            _marker: std::marker::PhantomData,
+        };
+        sub.hash_node(node);
+        sub.hasher.finish()
+    }
+
+    fn hash_kind(&mut self, kind: NodeKind) {
+        kind.hash(&mut self.hasher);
+    }
+
+    fn hash_op(&mut self, op: ast::Operator) {
+        op.hash(&mut self.hasher);
+    }
+}
+
+/// Structural (span-insensitive) equality over AST nodes.
+///
+/// Mirrors [`SpanlessHash`]: two nodes are [`SpanlessEq`]-equal if they have the same shape —
+/// same operators, literal values, identifier names, and children — regardless of where either
+/// one sits in the source. [`eq`] hashes both trees once up front as a cheap early-out before
+/// paying for the full recursive comparison; the recursive comparison itself never re-hashes.
+///
+/// Node kinds this doesn't yet know how to compare structurally (anything beyond what's listed
+/// below) are conservatively treated as unequal rather than guessed at.
+///
+/// [`eq`]: SpanlessEq::eq
+pub struct SpanlessEq;
+
+impl SpanlessEq {
+    pub fn eq<'a, N>(left: &'a N, right: &'a N) -> bool
+    where
+        N: AstNode,
+    {
+        let left = left.as_any_node_ref();
+        let right = right.as_any_node_ref();
+
+        if SpanlessHash::of_ref(left) != SpanlessHash::of_ref(right) {
+            return false;
+        }
+
+        Self::eq_any(left, right, 0)
+    }
+
+    /// The recursive half of [`eq`]: assumes the whole-tree hash early-out already passed, so it
+    /// only checks node kinds and payloads as it descends, without hashing again at each level.
+    ///
+    /// [`eq`]: SpanlessEq::eq
+    fn eq_any<'a>(left: AnyNodeRef<'a>, right: AnyNodeRef<'a>, depth: u32) -> bool {
+        if left.kind() != right.kind() {
+            return false;
+        }
+
+        if depth >= MAX_DEPTH {
+            return true;
+        }
+
+        match (left, right) {
+            (
+                AnyNodeRef::ExprBinOp(ast::ExprBinOp {
+                    op: left_op,
+                    left: left_l,
+                    right: left_r,
+                    ..
+                }),
+                AnyNodeRef::ExprBinOp(ast::ExprBinOp {
+                    op: right_op,
+                    left: right_l,
+                    right: right_r,
+                    ..
+                }),
+            ) => {
+                left_op == right_op
+                    && Self::eq_any(left_l.as_any_node_ref(), right_l.as_any_node_ref(), depth + 1)
+                    && Self::eq_any(left_r.as_any_node_ref(), right_r.as_any_node_ref(), depth + 1)
+            }
+            (
+                AnyNodeRef::ExprCompare(ast::ExprCompare {
+                    left: left_l,
+                    ops: left_ops,
+                    comparators: left_c,
+                    ..
+                }),
+                AnyNodeRef::ExprCompare(ast::ExprCompare {
+                    left: right_l,
+                    ops: right_ops,
+                    comparators: right_c,
+                    ..
+                }),
+            ) => {
+                left_ops == right_ops
+                    && left_c.len() == right_c.len()
+                    && Self::eq_any(left_l.as_any_node_ref(), right_l.as_any_node_ref(), depth + 1)
+                    && left_c
+                        .iter()
+                        .zip(right_c.iter())
+                        .all(|(l, r)| Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1))
+            }
+            (
+                AnyNodeRef::ExprName(ast::ExprName { id: left_id, .. }),
+                AnyNodeRef::ExprName(ast::ExprName { id: right_id, .. }),
+            ) => left_id == right_id,
+            (AnyNodeRef::ExprNumberLiteral(l), AnyNodeRef::ExprNumberLiteral(r)) => {
+                format!("{l:?}") == format!("{r:?}")
+            }
+            (
+                AnyNodeRef::ExprStringLiteral(ast::ExprStringLiteral { value: l, .. }),
+                AnyNodeRef::ExprStringLiteral(ast::ExprStringLiteral { value: r, .. }),
+            ) => l.to_str() == r.to_str(),
+            (
+                AnyNodeRef::ExprBooleanLiteral(ast::ExprBooleanLiteral { value: l, .. }),
+                AnyNodeRef::ExprBooleanLiteral(ast::ExprBooleanLiteral { value: r, .. }),
+            ) => l == r,
+            (AnyNodeRef::ExprNoneLiteral(_), AnyNodeRef::ExprNoneLiteral(_)) => true,
+            (
+                AnyNodeRef::ExprCall(ast::ExprCall {
+                    func: left_func,
+                    arguments: left_args,
+                    ..
+                }),
+                AnyNodeRef::ExprCall(ast::ExprCall {
+                    func: right_func,
+                    arguments: right_args,
+                    ..
+                }),
+            ) => {
+                left_args.args.len() == right_args.args.len()
+                    && left_args.keywords.len() == right_args.keywords.len()
+                    && Self::eq_any(left_func.as_any_node_ref(), right_func.as_any_node_ref(), depth + 1)
+                    && left_args
+                        .args
+                        .iter()
+                        .zip(right_args.args.iter())
+                        .all(|(l, r)| Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1))
+                    && left_args.keywords.iter().zip(right_args.keywords.iter()).all(
+                        |(l, r)| {
+                            l.arg.as_ref().map(|arg| &arg.id) == r.arg.as_ref().map(|arg| &arg.id)
+                                && Self::eq_any(
+                                    l.value.as_any_node_ref(),
+                                    r.value.as_any_node_ref(),
+                                    depth + 1,
+                                )
+                        },
+                    )
+            }
+            (
+                AnyNodeRef::ExprAttribute(ast::ExprAttribute {
+                    value: left_value,
+                    attr: left_attr,
+                    ..
+                }),
+                AnyNodeRef::ExprAttribute(ast::ExprAttribute {
+                    value: right_value,
+                    attr: right_attr,
+                    ..
+                }),
+            ) => {
+                left_attr.id == right_attr.id
+                    && Self::eq_any(left_value.as_any_node_ref(), right_value.as_any_node_ref(), depth + 1)
+            }
+            (
+                AnyNodeRef::ExprStarred(ast::ExprStarred { value: l, .. }),
+                AnyNodeRef::ExprStarred(ast::ExprStarred { value: r, .. }),
+            ) => Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1),
+            // Unordered containers: every element on one side must have a structural match on
+            // the other, regardless of position.
+            (AnyNodeRef::ExprSet(ast::ExprSet { elts: left_elts, .. }), AnyNodeRef::ExprSet(ast::ExprSet { elts: right_elts, .. })) => {
+                Self::eq_unordered(left_elts, right_elts, depth)
+            }
+            (AnyNodeRef::ExprList(ast::ExprList { elts: left_elts, .. }), AnyNodeRef::ExprList(ast::ExprList { elts: right_elts, .. }))
+            | (
+                AnyNodeRef::ExprTuple(ast::ExprTuple { elts: left_elts, .. }),
+                AnyNodeRef::ExprTuple(ast::ExprTuple { elts: right_elts, .. }),
+            ) => {
+                left_elts.len() == right_elts.len()
+                    && left_elts
+                        .iter()
+                        .zip(right_elts.iter())
+                        .all(|(l, r)| Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1))
+            }
+            (AnyNodeRef::ExprDict(ast::ExprDict { items: left_items, .. }), AnyNodeRef::ExprDict(ast::ExprDict { items: right_items, .. })) => {
+                Self::match_bijective(left_items, right_items, depth, |l, r, depth| {
+                    Self::eq_opt(l.key.as_ref(), r.key.as_ref(), depth)
+                        && Self::eq_any(l.value.as_any_node_ref(), r.value.as_any_node_ref(), depth + 1)
+                })
+            }
+            (AnyNodeRef::StmtPass(_), AnyNodeRef::StmtPass(_))
+            | (AnyNodeRef::StmtBreak(_), AnyNodeRef::StmtBreak(_))
+            | (AnyNodeRef::StmtContinue(_), AnyNodeRef::StmtContinue(_)) => true,
+            (
+                AnyNodeRef::StmtIf(ast::StmtIf {
+                    test: left_test,
+                    body: left_body,
+                    elif_else_clauses: left_clauses,
+                    ..
+                }),
+                AnyNodeRef::StmtIf(ast::StmtIf {
+                    test: right_test,
+                    body: right_body,
+                    elif_else_clauses: right_clauses,
+                    ..
+                }),
+            ) => {
+                Self::eq_any(left_test.as_any_node_ref(), right_test.as_any_node_ref(), depth + 1)
+                    && Self::eq_body(left_body, right_body, depth)
+                    && left_clauses.len() == right_clauses.len()
+                    && left_clauses.iter().zip(right_clauses.iter()).all(|(l, r)| {
+                        Self::eq_opt(l.test.as_ref(), r.test.as_ref(), depth)
+                            && Self::eq_body(&l.body, &r.body, depth)
+                    })
+            }
+            (AnyNodeRef::StmtExpr(ast::StmtExpr { value: l, .. }), AnyNodeRef::StmtExpr(ast::StmtExpr { value: r, .. })) => {
+                Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1)
+            }
+            (AnyNodeRef::StmtReturn(ast::StmtReturn { value: l, .. }), AnyNodeRef::StmtReturn(ast::StmtReturn { value: r, .. })) => {
+                Self::eq_opt(l.as_deref(), r.as_deref(), depth)
+            }
+            // Anything we don't special-case above, we don't claim to understand structurally.
+            _ => false,
+        }
+    }
+
+    fn eq_opt<'a, N>(left: Option<&'a N>, right: Option<&'a N>, depth: u32) -> bool
+    where
+        N: AstNode,
+    {
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                Self::eq_any(left.as_any_node_ref(), right.as_any_node_ref(), depth + 1)
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn eq_body(left: &[ast::Stmt], right: &[ast::Stmt], depth: u32) -> bool {
+        left.len() == right.len()
+            && left
+                .iter()
+                .zip(right.iter())
+                .all(|(l, r)| Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1))
+    }
+
+    fn eq_unordered(left: &[ast::Expr], right: &[ast::Expr], depth: u32) -> bool {
+        Self::match_bijective(left, right, depth, |l, r, depth| {
+            Self::eq_any(l.as_any_node_ref(), r.as_any_node_ref(), depth + 1)
+        })
+    }
+
+    /// A true bijective (multiset) comparison: every element of `left` is matched against a
+    /// *distinct* element of `right`, rather than each left element independently checking
+    /// whether `right` contains *some* match. Without consuming matched candidates, `{x, x}`
+    /// would wrongly compare equal to `{x, y}` (both "every left element has some match in
+    /// right"), even though they're different sets.
+    fn match_bijective<T>(
+        left: &[T],
+        right: &[T],
+        depth: u32,
+        mut eq: impl FnMut(&T, &T, u32) -> bool,
+    ) -> bool {
+        if left.len() != right.len() {
+            return false;
+        }
+
+        let mut unmatched: Vec<&T> = right.iter().collect();
+        for l in left {
+            let Some(pos) = unmatched.iter().position(|r| eq(l, r, depth)) else {
+                return false;
+            };
+            unmatched.remove(pos);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruff_python_ast as ast;
+    use ruff_python_parser::parse_expression;
+
+    use super::{SpanlessEq, SpanlessHash};
+
+    fn parse(source: &str) -> ast::Expr {
+        *parse_expression(source, "<test>").unwrap()
+    }
+
+    #[test]
+    fn identical_shape_different_position() {
+        let a = parse("[1, 2] + foo");
+        let b = parse("   [1,  2] + foo");
+        assert_eq!(SpanlessHash::of(&a), SpanlessHash::of(&b));
+        assert!(SpanlessEq::eq(&a, &b));
+    }
+
+    #[test]
+    fn different_identifier_is_unequal() {
+        let a = parse("[1, 2] + foo");
+        let b = parse("[1, 2] + bar");
+        assert!(!SpanlessEq::eq(&a, &b));
+    }
+
+    #[test]
+    fn set_literals_are_order_independent() {
+        let a = parse("{1, 2, 3}");
+        let b = parse("{3, 1, 2}");
+        assert_eq!(SpanlessHash::of(&a), SpanlessHash::of(&b));
+        assert!(SpanlessEq::eq(&a, &b));
+    }
+
+    #[test]
+    fn list_literals_are_order_dependent() {
+        let a = parse("[1, 2]");
+        let b = parse("[2, 1]");
+        assert!(!SpanlessEq::eq(&a, &b));
+    }
+
+    #[test]
+    fn set_comparison_is_bijective_not_just_subset() {
+        // Regression test: a one-directional "every left element has some match in right" check
+        // would wrongly call these equal, since both of `{x, x}`'s elements have *some* match in
+        // `{x, y}`. A real set, `{x, x}` only has one distinct member, so it isn't.
+        let a = parse("{x, x}");
+        let b = parse("{x, y}");
+        assert!(!SpanlessEq::eq(&a, &b));
+    }
+
+    #[test]
+    fn dict_comparison_is_bijective_not_just_subset() {
+        let a = parse("{1: 1, 1: 1}");
+        let b = parse("{1: 1, 2: 2}");
+        assert!(!SpanlessEq::eq(&a, &b));
+    }
+
+    #[test]
+    fn nested_dicts_still_respect_depth_limit() {
+        // Regression test: hashing dict keys/values used to go through the depth-resetting
+        // `SpanlessHash::of`, so `MAX_DEPTH` never kicked in for a chain of nested dicts. A dict
+        // nested a few levels deep still produces a stable digest; and since each nested value
+        // sits one level deeper, 200 levels go past `MAX_DEPTH` (128), so leaves that differ only
+        // below the limit hash the same.
+        let a = parse("{0: {0: {0: 'a'}}}");
+        let b = parse("{0: {0: {0: 'a'}}}");
+        assert_eq!(SpanlessHash::of(&a), SpanlessHash::of(&b));
+        assert!(SpanlessEq::eq(&a, &b));
+
+        let deep = |leaf: &str| format!("{}{leaf}{}", "{0: ".repeat(200), "}".repeat(200));
+        assert_eq!(
+            SpanlessHash::of(&parse(&deep("'a'"))),
+            SpanlessHash::of(&parse(&deep("'b'")))
+        );
+    }
+}